@@ -0,0 +1,103 @@
+/// Which checksum a framed packet is trailed with.
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum Crc {
+    Crc16,
+    Crc32,
+}
+
+impl std::str::FromStr for Crc {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "crc16" => Ok(Crc::Crc16),
+            "crc32" => Ok(Crc::Crc32),
+            other => Err(format!("unknown CRC kind: {}", other)),
+        }
+    }
+}
+
+/// CRC-16-CCITT (poly 0x1021, init 0xFFFF), the classic framing checksum
+/// for embedded serial links.
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// CRC-32 (poly 0xEDB88320, init/final XOR 0xFFFFFFFF), the same variant
+/// used by zlib/Ethernet.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xFFFFFFFF;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn checksum_bytes(crc: Crc, data: &[u8]) -> Vec<u8> {
+    match crc {
+        Crc::Crc16 => crc16(data).to_be_bytes().to_vec(),
+        Crc::Crc32 => crc32(data).to_be_bytes().to_vec(),
+    }
+}
+
+/// COBS-encodes `data` (Consistent Overhead Byte Stuffing): every run of
+/// non-zero bytes is prefixed with an overhead byte giving the distance to
+/// the next zero, so the only zero byte left in the output is the frame
+/// delimiter appended by [`frame`]. Runs longer than 254 bytes are split
+/// with an extra overhead byte, per the COBS spec.
+fn cobs_encode(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + data.len() / 254 + 2);
+    let mut code_index = 0;
+    out.push(0); // placeholder overhead byte, patched in below
+    let mut code: u8 = 1;
+
+    for &byte in data {
+        if byte == 0 {
+            out[code_index] = code;
+            code_index = out.len();
+            out.push(0);
+            code = 1;
+        } else {
+            out.push(byte);
+            code += 1;
+            if code == 0xFF {
+                out[code_index] = code;
+                code_index = out.len();
+                out.push(0);
+                code = 1;
+            }
+        }
+    }
+    out[code_index] = code;
+    out
+}
+
+/// Frames `payload` as `COBS(payload ++ crc(payload))` terminated by a
+/// `0x00` delimiter, so a receiver can resynchronize after corruption by
+/// scanning forward to the next zero byte.
+pub(crate) fn frame(payload: &[u8], crc: Crc) -> Vec<u8> {
+    let mut body = payload.to_vec();
+    body.extend_from_slice(&checksum_bytes(crc, payload));
+
+    let mut framed = cobs_encode(&body);
+    framed.push(0x00);
+    framed
+}
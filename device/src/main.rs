@@ -1,10 +1,16 @@
+mod framing;
+mod transport;
+
 use chrono::{SecondsFormat, Utc};
 use clap::{App, Arg};
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::VecDeque;
 use std::error::Error;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 use std::{
     fs::File,
     io::{BufRead, BufReader},
@@ -13,10 +19,11 @@ use std::{
 };
 use tokio::sync::mpsc;
 use tokio::time::{self, Duration};
+use transport::{HttpTransport, MqttTransport, SerialTransport, ShmemTransport, Transport};
 use uuid::Uuid;
 
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
-enum Severity {
+pub(crate) enum Severity {
     Debug,
     Info,
     Warning,
@@ -25,25 +32,55 @@ enum Severity {
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
-enum MessageType {
+pub(crate) enum MessageType {
     Log,
     SensorData,
+    ActionResponse,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-struct LogMessage {
+pub(crate) struct LogMessage {
     severity: Severity,
     message: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-struct SensorData {
+pub(crate) struct SensorData {
+    name: String,
+    // f64 so register-style u32/s16 sensor readings keep full precision;
+    // f32's ~7 significant digits silently lose bits above ~16.7M.
+    value: f64,
+}
+
+/// Lifecycle of a downlink command as the device works through it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ActionState {
+    Received,
+    InProgress,
+    Completed,
+    Failed,
+}
+
+/// Progress feedback for one in-flight downlink command, identified by the
+/// `action_id` the command arrived with.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct ActionResponse {
+    action_id: String,
+    state: ActionState,
+    progress: u8,
+}
+
+/// A downlink command as read from `--commands` or polled over HTTP. Only
+/// `action_id` is required; `name` is carried through for logging/display.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct Command {
+    action_id: String,
+    #[serde(default)]
     name: String,
-    value: f32,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-struct Message {
+pub(crate) struct Message {
     timestamp: String,
     device: String,
     firmware: String,
@@ -54,14 +91,172 @@ struct Message {
     #[serde(default)] // Makes the field optional during deserialization
     #[serde(skip_serializing_if = "Option::is_none")]
     sensor_data: Option<Vec<SensorData>>,
+    // Monotonic counter, set by stateful producers (e.g. the path sensor) so
+    // a consumer can detect dropped or reordered messages. Each path sensor
+    // keeps its own counter starting at 1, so a device with several paths
+    // emits one independent sequence per path, not one shared per device.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sequence: Option<u64>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    action_response: Option<ActionResponse>,
+}
+
+/// The number of states in the device behavior Markov chain.
+const NUM_DEVICE_STATES: usize = 4;
+
+/// Coarse behavioral states a simulated device can be in. Each state has its
+/// own message-emission rates and severity mix, so a device "feels" like it
+/// is idling, behaving normally, degrading, or actively faulting rather than
+/// emitting uniformly-random noise.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum DeviceState {
+    Idle,
+    Nominal,
+    Degraded,
+    Faulting,
+}
+
+impl DeviceState {
+    const ALL: [DeviceState; NUM_DEVICE_STATES] = [
+        DeviceState::Idle,
+        DeviceState::Nominal,
+        DeviceState::Degraded,
+        DeviceState::Faulting,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Per-state tick behavior: how likely the device is to emit each message
+/// kind on a given tick, the severity mix of any log message it emits, and
+/// how long (in ticks, on average) it tends to stay in this state.
+#[derive(Clone, Debug, Deserialize)]
+struct StateProfile {
+    log_emit_rate: f64,
+    sensor_emit_rate: f64,
+    // Categorical weights over [Debug, Info, Warning, Error].
+    severity_weights: [f64; 4],
+    mean_dwell_ticks: f64,
+}
+
+/// The device behavior Markov chain, loaded from `--markov-config` (or a
+/// `[markov]` table in a `--config` fleet file) instead of being hardcoded,
+/// so a run can be tuned without touching the binary. [`default_markov_config`]
+/// reproduces the original built-in behavior when no override is given.
+#[derive(Clone, Debug, Deserialize)]
+struct MarkovConfig {
+    idle: StateProfile,
+    nominal: StateProfile,
+    degraded: StateProfile,
+    faulting: StateProfile,
+    // Row-stochastic: `transition_matrix[i][j]` is the probability of
+    // moving from state `i` to state `j` once that state's dwell expires.
+    transition_matrix: [[f64; NUM_DEVICE_STATES]; NUM_DEVICE_STATES],
+}
+
+impl MarkovConfig {
+    fn state_profile(&self, state: DeviceState) -> &StateProfile {
+        match state {
+            DeviceState::Idle => &self.idle,
+            DeviceState::Nominal => &self.nominal,
+            DeviceState::Degraded => &self.degraded,
+            DeviceState::Faulting => &self.faulting,
+        }
+    }
+}
+
+/// The hardcoded defaults this simulator originally shipped with, used when
+/// no `--markov-config`/`[markov]` override is supplied.
+fn default_markov_config() -> MarkovConfig {
+    MarkovConfig {
+        idle: StateProfile {
+            log_emit_rate: 0.02,
+            sensor_emit_rate: 0.2,
+            severity_weights: [0.7, 0.3, 0.0, 0.0],
+            mean_dwell_ticks: 40.0,
+        },
+        nominal: StateProfile {
+            log_emit_rate: 0.2,
+            sensor_emit_rate: 1.0,
+            severity_weights: [0.2, 0.7, 0.1, 0.0],
+            mean_dwell_ticks: 80.0,
+        },
+        degraded: StateProfile {
+            log_emit_rate: 0.5,
+            sensor_emit_rate: 1.0,
+            severity_weights: [0.05, 0.25, 0.5, 0.2],
+            mean_dwell_ticks: 20.0,
+        },
+        faulting: StateProfile {
+            log_emit_rate: 0.9,
+            sensor_emit_rate: 0.6,
+            severity_weights: [0.0, 0.05, 0.25, 0.7],
+            mean_dwell_ticks: 8.0,
+        },
+        transition_matrix: [
+            // Idle
+            [0.6, 0.35, 0.05, 0.0],
+            // Nominal
+            [0.1, 0.7, 0.15, 0.05],
+            // Degraded
+            [0.05, 0.25, 0.5, 0.2],
+            // Faulting
+            [0.0, 0.1, 0.3, 0.6],
+        ],
+    }
+}
+
+/// Loads a `MarkovConfig` from a standalone TOML file, as pointed to by
+/// `--markov-config`.
+fn load_markov_config(path: &str) -> Result<MarkovConfig, Box<dyn Error>> {
+    let contents = std::fs::read_to_string(path)?;
+    Ok(toml::from_str(&contents)?)
+}
+
+fn sample_categorical(rng: &mut StdRng, weights: &[f64]) -> usize {
+    let total: f64 = weights.iter().sum();
+    let mut x = rng.gen::<f64>() * total;
+    for (i, w) in weights.iter().enumerate() {
+        if x < *w {
+            return i;
+        }
+        x -= w;
+    }
+    weights.len() - 1
+}
+
+fn sample_severity(rng: &mut StdRng, profile: &StateProfile) -> Severity {
+    match sample_categorical(rng, &profile.severity_weights) {
+        0 => Severity::Debug,
+        1 => Severity::Info,
+        2 => Severity::Warning,
+        _ => Severity::Error,
+    }
+}
+
+fn sample_next_state(rng: &mut StdRng, markov: &MarkovConfig, current: DeviceState) -> DeviceState {
+    let row = markov.transition_matrix[current.index()];
+    DeviceState::ALL[sample_categorical(rng, &row)]
+}
+
+/// Draws a dwell time (in ticks) from a geometric distribution with the
+/// given mean, via inverse-CDF sampling. Always returns at least one tick.
+fn sample_dwell_ticks(rng: &mut StdRng, mean_ticks: f64) -> u32 {
+    let p = (1.0 / mean_ticks.max(1.0)).clamp(0.001, 0.999);
+    let u: f64 = rng.gen();
+    let ticks = ((1.0 - u).ln() / (1.0 - p).ln()).ceil();
+    (ticks as u32).max(1)
 }
 
 async fn send_messages(
     mut messages: Vec<Message>,
-    port: u16,
+    transport: &dyn Transport,
     buffer_size: u64,
 ) -> Result<(), Box<dyn Error>> {
-    let agent = ureq::Agent::new();
     messages.sort_by(|a, b| {
         match (&a.message_type, &b.message_type) {
             // Prioritize Log messages with Severity::Error
@@ -88,8 +283,9 @@ async fn send_messages(
                     std::cmp::Ordering::Equal
                 }
             }
-            // SensorData comes after Log messages with Severity::Error but before other logs
-            (MessageType::Log, MessageType::SensorData) => {
+            // SensorData and ActionResponse come after Log messages with
+            // Severity::Error but before other logs
+            (MessageType::Log, MessageType::SensorData | MessageType::ActionResponse) => {
                 if a.log_message
                     .as_ref()
                     .map_or(false, |log| log.severity == Severity::Error)
@@ -99,7 +295,7 @@ async fn send_messages(
                     std::cmp::Ordering::Greater
                 }
             }
-            (MessageType::SensorData, MessageType::Log) => {
+            (MessageType::SensorData | MessageType::ActionResponse, MessageType::Log) => {
                 if b.log_message
                     .as_ref()
                     .map_or(false, |log| log.severity == Severity::Error)
@@ -109,8 +305,11 @@ async fn send_messages(
                     std::cmp::Ordering::Less
                 }
             }
-            // SensorData messages are considered equal among themselves
-            (MessageType::SensorData, MessageType::SensorData) => std::cmp::Ordering::Equal,
+            // SensorData and ActionResponse messages are considered equal among themselves
+            (
+                MessageType::SensorData | MessageType::ActionResponse,
+                MessageType::SensorData | MessageType::ActionResponse,
+            ) => std::cmp::Ordering::Equal,
         }
     });
 
@@ -123,157 +322,170 @@ async fn send_messages(
     }
 
     for message in messages.into_iter() {
-        match agent
-            .post(&format!("http://localhost:{}", port))
-            .set("Content-Type", "application/json")
-            .send_json(serde_json::to_value(message)?)
-        {
-            Ok(_) => {
-                println!("Message sent successfully");
-            }
-            Err(ureq::Error::Status(code, response)) => {
-                eprintln!(
-                    "Failed to send message. Code: {}, Status: {}",
-                    code,
-                    response.status()
-                );
-            }
-            Err(e) => {
-                eprintln!("Failed to send message without getting a response: {:?}", e);
-            }
+        if let Err(e) = transport.send(&message).await {
+            eprintln!("Failed to send message: {:?}", e);
         }
     }
 
     Ok(())
 }
 
-async fn simulate_messages(
-    port: u16,
-    log_interval_ms: u64,
-    sensor_interval_ms: u64,
-    write_interval_ms: u64,
-    buffer_size: u64,
+/// Runs one device's Markov-chain state machine: seeds an RNG deterministically
+/// from `base_seed` + `device_index`, emits `Log` messages at the current
+/// state's rate, and transitions state (dwell countdown + transition matrix
+/// lookup) via `markov`. Shared by both the flags-driven `--simulate` mode
+/// and `--config` device groups, so the state machine itself never drifts
+/// between the two. `emit_generic_sensor` additionally emits the flags-mode's
+/// generic "Temp1" reading at the state's sensor rate; `--config` mode
+/// reports sensors via its own configured producers instead, so it passes
+/// `false`.
+async fn run_markov_log_producer(
+    markov: Arc<MarkovConfig>,
+    tx: mpsc::Sender<Message>,
+    device_id: String,
+    firmware: String,
+    tick_interval: Duration,
+    base_seed: u64,
+    device_index: u64,
+    emit_generic_sensor: bool,
 ) {
-    let log_message_interval: Duration = Duration::from_millis(log_interval_ms);
-    let sensor_data_interval: Duration = Duration::from_millis(sensor_interval_ms);
-    let send_interval: Duration = Duration::from_millis(write_interval_ms);
+    let mut rng = StdRng::seed_from_u64(base_seed.wrapping_add(device_index));
+    let mut state = DeviceState::Idle;
+    let mut profile = markov.state_profile(state).clone();
+    let mut dwell_remaining = sample_dwell_ticks(&mut rng, profile.mean_dwell_ticks);
+    let mut ticker = time::interval(tick_interval);
 
-    println!("Creating device");
-    // make the channel be larger than the buffer size so we can filter
-    // messages in send_messages and pretend we are putting the messages into
-    // different quees based on priority
-    let (tx, mut rx) = mpsc::channel(2 * buffer_size as usize);
-    let device_id = Uuid::new_v4().to_string();
-
-    // Log Message Producer Task
-    let tx_clone = tx.clone();
-    let device_id_clone = device_id.clone();
-    tokio::spawn(async move {
-        let mut rng = StdRng::from_entropy(); // Create a random number generator
-        loop {
-            let random_number = rng.gen_range(1..3);
-            let message_type = if random_number == 1 {
-                Severity::Error
-            } else {
-                Severity::Info
-            };
+    loop {
+        ticker.tick().await;
 
+        if rng.gen_bool(profile.log_emit_rate.min(1.0)) {
             let log_msg = Message {
                 timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
-                device: device_id_clone.clone(),
-                firmware: "1.0-sim".to_string(),
+                device: device_id.clone(),
+                firmware: firmware.clone(),
                 message_type: MessageType::Log,
                 log_message: Some(LogMessage {
-                    severity: message_type,
+                    severity: sample_severity(&mut rng, &profile),
                     message: "This is a simulated message.".to_string(),
                 }),
                 sensor_data: None,
+                sequence: None,
+                action_response: None,
             };
-            // Send the log message
-            if let Err(e) = tx_clone.try_send(log_msg) {
+            if let Err(e) = tx.try_send(log_msg) {
                 eprintln!("Failed to put log message into buffer: {:?}", e);
             }
-            time::sleep(log_message_interval).await;
         }
-    });
 
-    // Sensor Data Producer Task
-    tokio::spawn(async move {
-        let mut rng = StdRng::from_entropy(); // Create a random number generator
-        loop {
+        if emit_generic_sensor && rng.gen_bool(profile.sensor_emit_rate.min(1.0)) {
             let sensor_msg = Message {
                 timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
                 device: device_id.clone(),
-                firmware: "1.0-sim".to_string(),
+                firmware: firmware.clone(),
                 message_type: MessageType::SensorData,
                 log_message: None,
                 sensor_data: Some(vec![SensorData {
                     name: "Temp1".to_string(),
                     value: rng.gen_range(1.0..100.0),
                 }]),
+                sequence: None,
+                action_response: None,
             };
+            if let Err(e) = tx.try_send(sensor_msg) {
+                eprintln!("Failed to put sensor message into buffer: {:?}", e);
+            }
+        }
 
-            tx.send(sensor_msg).await.unwrap();
-            time::sleep(sensor_data_interval).await;
+        dwell_remaining -= 1;
+        if dwell_remaining == 0 {
+            state = sample_next_state(&mut rng, &markov, state);
+            profile = markov.state_profile(state).clone();
+            dwell_remaining = sample_dwell_ticks(&mut rng, profile.mean_dwell_ticks);
         }
-    });
+    }
+}
 
-    // Central Message Sending Task
-    tokio::spawn(async move {
-        let mut buffer = VecDeque::new();
-        loop {
-            // Ensure we have at least one message to send
-            if let Some(message) = rx.recv().await {
+/// Runs one simulated device's Markov-chain traffic generator plus the
+/// shared buffering/sending task.
+async fn simulate_messages(
+    transport: Arc<dyn Transport>,
+    markov: Arc<MarkovConfig>,
+    tick_interval_ms: u64,
+    write_interval_ms: u64,
+    buffer_size: u64,
+    base_seed: u64,
+    device_index: u64,
+) {
+    let tick_interval: Duration = Duration::from_millis(tick_interval_ms);
+    let send_interval: Duration = Duration::from_millis(write_interval_ms);
+
+    println!("Creating device");
+    // make the channel be larger than the buffer size so we can filter
+    // messages in send_messages and pretend we are putting the messages into
+    // different quees based on priority
+    let (tx, rx) = mpsc::channel(2 * buffer_size as usize);
+    let device_id = Uuid::new_v4().to_string();
+
+    tokio::spawn(run_markov_log_producer(
+        markov,
+        tx,
+        device_id,
+        "1.0-sim".to_string(),
+        tick_interval,
+        base_seed,
+        device_index,
+        true,
+    ));
+
+    run_central_sender(rx, transport, buffer_size, send_interval).await;
+}
+
+/// Drains messages from `rx` as they arrive, batching whatever has
+/// accumulated since the last send, and hands each batch to
+/// [`send_messages`]. Shared by every simulation mode so the
+/// buffering/dropping behavior is consistent regardless of how messages
+/// are produced or which transport carries them.
+async fn run_central_sender(
+    mut rx: mpsc::Receiver<Message>,
+    transport: Arc<dyn Transport>,
+    buffer_size: u64,
+    send_interval: Duration,
+) {
+    let mut buffer = VecDeque::new();
+    loop {
+        // Ensure we have at least one message to send
+        if let Some(message) = rx.recv().await {
+            buffer.push_back(message);
+            // Drain all available messages from the channel
+            while let Ok(message) = rx.try_recv() {
                 buffer.push_back(message);
-                // Drain all available messages from the channel
-                while let Ok(message) = rx.try_recv() {
-                    buffer.push_back(message);
-                }
-                // Call send_messages with all collected messages
-                if let Err(e) = send_messages(buffer.drain(..).collect(), port, buffer_size).await {
-                    eprintln!("Failed to send messages: {:?}", e);
-                    break;
-                }
-            } else {
-                // Channel is closed
+            }
+            // Call send_messages with all collected messages
+            if let Err(e) =
+                send_messages(buffer.drain(..).collect(), transport.as_ref(), buffer_size).await
+            {
+                eprintln!("Failed to send messages: {:?}", e);
                 break;
             }
-            time::sleep(send_interval).await;
+        } else {
+            // Channel is closed
+            break;
         }
-    })
-    .await
-    .unwrap();
+        time::sleep(send_interval).await;
+    }
 }
 
-fn send_message_file(message: &Message, port: u16) -> Result<(), Box<dyn Error>> {
-    let agent = ureq::Agent::new();
-    match agent
-        .post(&format!("http://localhost:{}", port))
-        .set("Content-Type", "application/json")
-        .send_json(serde_json::to_value(message)?)
-    {
-        Ok(_) => {
-            println!("Message sent successfully");
-        }
-        Err(ureq::Error::Status(code, response)) => {
-            eprintln!(
-                "Failed to send message. Code: {}, Status: {}",
-                code,
-                response.status()
-            );
-        }
-        Err(_) => {
-            eprintln!("Failed to send message without getting a response");
-        }
+async fn send_message_file(message: &Message, transport: &dyn Transport) -> Result<(), Box<dyn Error>> {
+    if let Err(e) = transport.send(message).await {
+        eprintln!("Failed to send message: {:?}", e);
     }
-
     Ok(())
 }
 
-fn send_messages_from_file(
+async fn send_messages_from_file(
     file_path: &str,
     interval: u64,
-    port: u16,
+    transport: Arc<dyn Transport>,
 ) -> Result<(), Box<dyn Error>> {
     let path = Path::new(&file_path);
     let file = File::open(path)?;
@@ -283,15 +495,530 @@ fn send_messages_from_file(
         let line = line?;
         let message: Result<Message, serde_json::Error> = serde_json::from_str(&line);
         match message {
-            Ok(m) => send_message_file(&m, port)?,
+            Ok(m) => send_message_file(&m, transport.as_ref()).await?,
             Err(e) => eprintln!("Error parsing line: {}", e),
         }
-        thread::sleep(Duration::from_secs(interval));
+        time::sleep(Duration::from_secs(interval)).await;
+    }
+
+    Ok(())
+}
+
+/// A declarative fleet specification loaded via `--config`. Each entry in
+/// `device` describes one group of identical devices (a count, a firmware
+/// string, a buffer size) and the set of sensors each device in that group
+/// reports. `markov`, if present, overrides the built-in state profiles and
+/// transition matrix ([`default_markov_config`]) for every device group.
+#[derive(Debug, Deserialize)]
+struct FleetConfig {
+    device: Vec<DeviceGroupConfig>,
+    #[serde(default)]
+    markov: Option<MarkovConfig>,
+}
+
+fn default_buffer_size() -> u64 {
+    3
+}
+
+#[derive(Debug, Deserialize)]
+struct DeviceGroupConfig {
+    count: u64,
+    firmware: String,
+    #[serde(default = "default_buffer_size")]
+    buffer_size: u64,
+    #[serde(default, rename = "sensor")]
+    sensors: Vec<SensorConfig>,
+    #[serde(default, rename = "path")]
+    paths: Vec<PathSensorConfig>,
+}
+
+/// The numeric type a sensor's generated values are constrained to, so a
+/// config can describe a register-style `u32` counter alongside a `f32`
+/// temperature reading.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SensorValueType {
+    U16,
+    S16,
+    U32,
+    F32,
+}
+
+impl SensorValueType {
+    /// Rounds and clamps a raw sampled value to this type's representable
+    /// range. `f32` sensors are passed through unrounded.
+    fn constrain(self, raw: f64) -> f64 {
+        match self {
+            SensorValueType::U16 => raw.round().clamp(0.0, u16::MAX as f64),
+            SensorValueType::S16 => raw.round().clamp(i16::MIN as f64, i16::MAX as f64),
+            SensorValueType::U32 => raw.round().clamp(0.0, u32::MAX as f64),
+            SensorValueType::F32 => raw,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SensorConfig {
+    name: String,
+    #[serde(rename = "type")]
+    value_type: SensorValueType,
+    #[serde(deserialize_with = "deserialize_duration")]
+    period: Duration,
+    #[serde(default = "default_scale")]
+    scale: f64,
+    #[serde(deserialize_with = "deserialize_range")]
+    range: [f32; 2],
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+/// Parses a sensor's `range = [min, max]` and rejects `min > max`, which
+/// would otherwise reach `rng.gen_range` and panic at runtime.
+fn deserialize_range<'de, D>(deserializer: D) -> Result<[f32; 2], D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let range = <[f32; 2]>::deserialize(deserializer)?;
+    if range[0] > range[1] {
+        return Err(serde::de::Error::custom(format!(
+            "range min ({}) must not be greater than max ({})",
+            range[0], range[1]
+        )));
+    }
+    Ok(range)
+}
+
+/// Parses a human duration string like `"3s"` or `"500ms"` as used in a
+/// sensor's or path's `period` field. Rejects zero, since `time::interval`
+/// panics on a zero-duration period; shared by [`SensorConfig`] and
+/// [`PathSensorConfig`] so the check only needs to live in one place.
+fn deserialize_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let raw = String::deserialize(deserializer)?;
+    let duration = humantime::parse_duration(&raw).map_err(serde::de::Error::custom)?;
+    if duration.is_zero() {
+        return Err(serde::de::Error::custom("period must be greater than zero"));
+    }
+    Ok(duration)
+}
+
+/// Derives a deterministic RNG seed for one configured sensor, distinct per
+/// device and per sensor within that device, so a `--config ... --seed N`
+/// run reproduces identical sensor readings. Offset by at least 7919 from
+/// `base_seed`, so it does not collide with the log producer's own
+/// `base_seed + device_index` seed in fleets of up to 7919 devices.
+fn sensor_seed(base_seed: u64, device_index: u64, sensor_index: u64) -> u64 {
+    base_seed
+        .wrapping_add(device_index.wrapping_mul(1_000_003))
+        .wrapping_add((sensor_index + 1).wrapping_mul(7919))
+}
+
+/// Ticks forever at `sensor.period`, emitting one sensor reading per tick
+/// drawn uniformly from `sensor.range`, scaled and constrained to the
+/// sensor's numeric type. Seeded deterministically from `base_seed`,
+/// `device_index` and `sensor_index` so runs are reproducible.
+async fn simulate_configured_sensor(
+    sensor: SensorConfig,
+    device_id: String,
+    firmware: String,
+    tx: mpsc::Sender<Message>,
+    base_seed: u64,
+    device_index: u64,
+    sensor_index: u64,
+) {
+    let mut rng = StdRng::seed_from_u64(sensor_seed(base_seed, device_index, sensor_index));
+    let mut ticker = time::interval(sensor.period);
+    let (low, high) = (sensor.range[0] as f64, sensor.range[1] as f64);
+    loop {
+        ticker.tick().await;
+        let raw = rng.gen_range(low..=high) * sensor.scale;
+        let value = sensor.value_type.constrain(raw);
+
+        let message = Message {
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            device: device_id.clone(),
+            firmware: firmware.clone(),
+            message_type: MessageType::SensorData,
+            log_message: None,
+            sensor_data: Some(vec![SensorData {
+                name: sensor.name.clone(),
+                value,
+            }]),
+            sequence: None,
+            action_response: None,
+        };
+        if let Err(e) = tx.try_send(message) {
+            eprintln!("Failed to put sensor message into buffer: {:?}", e);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct PathWaypoint {
+    lat: f64,
+    lon: f64,
+}
+
+/// A device that moves along an ordered, wrapping list of waypoints rather
+/// than emitting independent random scalars. `interpolation_steps` is the
+/// number of points reported per leg, starting at the leg's first waypoint:
+/// `n` reports that waypoint plus `n - 1` evenly spaced intermediate points
+/// (0 or 1 reports only the waypoints themselves).
+#[derive(Debug, Clone, Deserialize)]
+struct PathSensorConfig {
+    name: String,
+    // Shares `deserialize_duration` with `SensorConfig::period`, which
+    // rejects a zero period - `time::interval(path.period)` below would
+    // otherwise panic on a misconfigured "0s" path.
+    #[serde(deserialize_with = "deserialize_duration")]
+    period: Duration,
+    waypoints: Vec<PathWaypoint>,
+    #[serde(default)]
+    interpolation_steps: u32,
+}
+
+/// Great-circle distance (meters) and initial bearing (degrees, 0-360) from
+/// `from` to `to`, via the haversine formula.
+fn path_kinematics(from: PathWaypoint, to: PathWaypoint) -> (f64, f64) {
+    const EARTH_RADIUS_M: f64 = 6_371_000.0;
+
+    let (lat1, lon1) = (from.lat.to_radians(), from.lon.to_radians());
+    let (lat2, lon2) = (to.lat.to_radians(), to.lon.to_radians());
+    let (dlat, dlon) = (lat2 - lat1, lon2 - lon1);
+
+    let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    let distance = EARTH_RADIUS_M * 2.0 * a.sqrt().asin();
+
+    let y = dlon.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
+    let heading = y.atan2(x).to_degrees().rem_euclid(360.0);
+
+    (distance, heading)
+}
+
+/// Ticks forever at `path.period`, advancing a device along its waypoint
+/// path (wrapping at the end) and reporting position, derived speed and
+/// heading, and a sequence number that increases by one on every emission
+/// so a consumer can detect drops or reordering.
+async fn simulate_path_sensor(
+    path: PathSensorConfig,
+    device_id: String,
+    firmware: String,
+    tx: mpsc::Sender<Message>,
+) {
+    if path.waypoints.len() < 2 {
+        eprintln!("Path sensor {} needs at least 2 waypoints; skipping", path.name);
+        return;
+    }
+
+    let steps = path.interpolation_steps.max(1);
+    let mut ticker = time::interval(path.period);
+    let mut waypoint_index = 0usize;
+    let mut step = 0u32;
+    let mut sequence = 0u64;
+
+    loop {
+        ticker.tick().await;
+
+        let from = path.waypoints[waypoint_index];
+        let to = path.waypoints[(waypoint_index + 1) % path.waypoints.len()];
+        let fraction = step as f64 / steps as f64;
+        let lat = from.lat + (to.lat - from.lat) * fraction;
+        let lon = from.lon + (to.lon - from.lon) * fraction;
+        let (leg_distance_m, heading) = path_kinematics(from, to);
+        let speed_mps = leg_distance_m / (steps as f64 * path.period.as_secs_f64()).max(f64::EPSILON);
+
+        sequence += 1;
+        let message = Message {
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            device: device_id.clone(),
+            firmware: firmware.clone(),
+            message_type: MessageType::SensorData,
+            log_message: None,
+            sensor_data: Some(vec![
+                SensorData {
+                    name: format!("{}.lat", path.name),
+                    value: lat,
+                },
+                SensorData {
+                    name: format!("{}.lon", path.name),
+                    value: lon,
+                },
+                SensorData {
+                    name: format!("{}.speed", path.name),
+                    value: speed_mps,
+                },
+                SensorData {
+                    name: format!("{}.heading", path.name),
+                    value: heading,
+                },
+            ]),
+            sequence: Some(sequence),
+            action_response: None,
+        };
+        if let Err(e) = tx.try_send(message) {
+            eprintln!("Failed to put path sensor message into buffer: {:?}", e);
+        }
+
+        step += 1;
+        if step >= steps {
+            step = 0;
+            waypoint_index = (waypoint_index + 1) % path.waypoints.len();
+        }
+    }
+}
+
+/// Spawns every device in one `[[device]]` group: a Markov-chain log
+/// producer per device (seeded from `base_seed` + that device's global
+/// index) plus one producer task per configured sensor, all feeding a
+/// shared buffer/send task.
+async fn run_device_group(
+    group: DeviceGroupConfig,
+    transport: Arc<dyn Transport>,
+    markov: Arc<MarkovConfig>,
+    base_seed: u64,
+    device_offset: u64,
+) {
+    let mut devices = Vec::new();
+
+    for i in 0..group.count {
+        let device_index = device_offset + i;
+        let device_id = Uuid::new_v4().to_string();
+        let (tx, rx) = mpsc::channel(2 * group.buffer_size as usize);
+
+        tokio::spawn(run_markov_log_producer(
+            Arc::clone(&markov),
+            tx.clone(),
+            device_id.clone(),
+            group.firmware.clone(),
+            Duration::from_millis(100),
+            base_seed,
+            device_index,
+            false,
+        ));
+
+        for (sensor_index, sensor) in group.sensors.clone().into_iter().enumerate() {
+            let tx = tx.clone();
+            let device_id = device_id.clone();
+            let firmware = group.firmware.clone();
+            tokio::spawn(async move {
+                simulate_configured_sensor(
+                    sensor,
+                    device_id,
+                    firmware,
+                    tx,
+                    base_seed,
+                    device_index,
+                    sensor_index as u64,
+                )
+                .await;
+            });
+        }
+
+        for path in group.paths.clone() {
+            let tx = tx.clone();
+            let device_id = device_id.clone();
+            let firmware = group.firmware.clone();
+            tokio::spawn(async move {
+                simulate_path_sensor(path, device_id, firmware, tx).await;
+            });
+        }
+        drop(tx);
+
+        let transport = Arc::clone(&transport);
+        let buffer_size = group.buffer_size;
+        devices.push(tokio::spawn(async move {
+            run_central_sender(rx, transport, buffer_size, Duration::from_millis(500)).await;
+        }));
+    }
+
+    for device in devices {
+        let _ = device.await;
+    }
+}
+
+/// Loads a `FleetConfig` from `config_path` and runs every device group it
+/// describes, replacing the scattered `--log-interval`/`--sensor-interval`/
+/// `--number` flags with one declarative fleet description.
+async fn run_config_simulation(
+    config_path: &str,
+    transport: Arc<dyn Transport>,
+    markov_override: Option<Arc<MarkovConfig>>,
+    base_seed: u64,
+) -> Result<(), Box<dyn Error>> {
+    let contents = std::fs::read_to_string(config_path)?;
+    let config: FleetConfig = toml::from_str(&contents)?;
+
+    // An explicit `--markov-config` wins; otherwise fall back to the fleet
+    // file's own `[markov]` table, then the built-in defaults.
+    let markov = Arc::new(markov_override.map_or_else(
+        || config.markov.clone().unwrap_or_else(default_markov_config),
+        |m| (*m).clone(),
+    ));
+
+    let mut device_offset = 0u64;
+    let mut groups = Vec::new();
+    for group in config.device {
+        let count = group.count;
+        groups.push(tokio::spawn(run_device_group(
+            group,
+            Arc::clone(&transport),
+            Arc::clone(&markov),
+            base_seed,
+            device_offset,
+        )));
+        device_offset += count;
+    }
+
+    for group in groups {
+        let _ = group.await;
+    }
+
+    Ok(())
+}
+
+/// Walks one command through its lifecycle, publishing an `ActionResponse`
+/// at each step so a backend can watch the action progress to completion.
+/// The terminal state is seeded deterministically from the command's own
+/// `action_id`, so most actions complete but some reproducibly fail,
+/// letting backends be tested against both terminal states.
+async fn run_action_response_sequence(
+    command: Command,
+    device_id: String,
+    firmware: String,
+    transport: Arc<dyn Transport>,
+) {
+    println!("Acting on command {} ({})", command.action_id, command.name);
+
+    let mut hasher = DefaultHasher::new();
+    command.action_id.hash(&mut hasher);
+    let mut rng = StdRng::seed_from_u64(hasher.finish());
+    let fails = rng.gen_bool(0.15);
+
+    let steps: [(ActionState, u8); 4] = [
+        (ActionState::Received, 0),
+        (ActionState::InProgress, 35),
+        (ActionState::InProgress, 70),
+        if fails {
+            (ActionState::Failed, 70)
+        } else {
+            (ActionState::Completed, 100)
+        },
+    ];
+
+    for (state, progress) in steps {
+        let message = Message {
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            device: device_id.clone(),
+            firmware: firmware.clone(),
+            message_type: MessageType::ActionResponse,
+            log_message: None,
+            sensor_data: None,
+            sequence: None,
+            action_response: Some(ActionResponse {
+                action_id: command.action_id.clone(),
+                state,
+                progress,
+            }),
+        };
+        if let Err(e) = transport.send(&message).await {
+            eprintln!("Failed to send action response: {:?}", e);
+        }
+        time::sleep(Duration::from_millis(750)).await;
+    }
+}
+
+/// Reads one command per line from `file_path` and spawns an independent
+/// response sequence for each, so several commands can be in flight (and
+/// interleaved with normal telemetry) at once.
+async fn run_commands_from_file(
+    file_path: String,
+    device_id: String,
+    firmware: String,
+    transport: Arc<dyn Transport>,
+) -> Result<(), Box<dyn Error>> {
+    let file = File::open(&file_path)?;
+    let reader = BufReader::new(file);
+
+    for line in reader.lines() {
+        let line = line?;
+        match serde_json::from_str::<Command>(&line) {
+            Ok(command) => {
+                let device_id = device_id.clone();
+                let firmware = firmware.clone();
+                let transport = Arc::clone(&transport);
+                tokio::spawn(async move {
+                    run_action_response_sequence(command, device_id, firmware, transport).await;
+                });
+            }
+            Err(e) => eprintln!("Error parsing command line: {}", e),
+        }
     }
 
     Ok(())
 }
 
+/// Polls `http://localhost:<port>/commands` for a JSON array of pending
+/// `Command`s, spawning a response sequence for each one not seen before.
+async fn poll_commands_http(port: u16, device_id: String, firmware: String, transport: Arc<dyn Transport>) {
+    let agent = ureq::Agent::new();
+    let mut seen_action_ids = std::collections::HashSet::new();
+    let mut ticker = time::interval(Duration::from_secs(2));
+
+    loop {
+        ticker.tick().await;
+
+        let response = match agent
+            .get(&format!("http://localhost:{}/commands", port))
+            .call()
+        {
+            Ok(response) => response,
+            Err(_) => continue, // No command endpoint available yet; keep polling.
+        };
+        let commands: Vec<Command> = match response.into_json() {
+            Ok(commands) => commands,
+            Err(e) => {
+                eprintln!("Failed to parse commands response: {:?}", e);
+                continue;
+            }
+        };
+
+        for command in commands {
+            if seen_action_ids.insert(command.action_id.clone()) {
+                let device_id = device_id.clone();
+                let firmware = firmware.clone();
+                let transport = Arc::clone(&transport);
+                tokio::spawn(async move {
+                    run_action_response_sequence(command, device_id, firmware, transport).await;
+                });
+            }
+        }
+    }
+}
+
+/// Starts the downlink command responder: reads commands from `--commands
+/// <file>` once if given, otherwise polls the HTTP commands endpoint
+/// forever. Runs independently of the telemetry-producing devices.
+fn spawn_command_responder(commands_file: Option<&str>, port: u16, transport: Arc<dyn Transport>) {
+    let device_id = Uuid::new_v4().to_string();
+    let firmware = "1.0-sim".to_string();
+
+    if let Some(commands_file) = commands_file {
+        let commands_file = commands_file.to_string();
+        tokio::spawn(async move {
+            if let Err(e) = run_commands_from_file(commands_file, device_id, firmware, transport).await {
+                eprintln!("Failed to read commands file: {:?}", e);
+            }
+        });
+    } else {
+        tokio::spawn(poll_commands_http(port, device_id, firmware, transport));
+    }
+}
+
 fn positive_integer_validator(val: String) -> Result<(), String> {
     val.parse::<i64>()
         .map_err(|_| "The value must be an integer.".to_string())
@@ -313,7 +1040,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 .short("f")
                 .long("file")
                 .takes_value(true)
-                .conflicts_with("simulate")
+                .conflicts_with_all(&["simulate", "config"])
                 .help("Path to the NDJSON file"),
         )
         .arg(
@@ -329,22 +1056,22 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 .short("s")
                 .long("simulate")
                 .takes_value(false)
+                .conflicts_with("config")
                 .help("Simulate message generation and sending"),
         )
         .arg(
-            Arg::with_name("log-interval")
-                .long("log-interval")
+            Arg::with_name("config")
+                .long("config")
                 .takes_value(true)
-                .requires("simulate")
-                .help("Time between log messages for a single device in ms (default: 500)")
-                .validator(positive_integer_validator),
+                .conflicts_with_all(&["file", "simulate"])
+                .help("Path to a TOML fleet config describing device groups and their sensors"),
         )
         .arg(
-            Arg::with_name("sensor-interval")
-                .long("sensor-interval")
+            Arg::with_name("tick-interval")
+                .long("tick-interval")
                 .takes_value(true)
                 .requires("simulate")
-                .help("Time between sensor messages for a single device in ms (default: 500)")
+                .help("Time between state-machine ticks for a single device in ms (default: 100)")
                 .validator(positive_integer_validator),
         )
         .arg(
@@ -372,6 +1099,12 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 .help("Number of devices to simulate (default: 3)")
                 .validator(positive_integer_validator),
         )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .takes_value(true)
+                .help("Base RNG seed for reproducible simulations (default: 0)"),
+        )
         .arg(
             Arg::with_name("port")
                 .short("p")
@@ -380,10 +1113,135 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 .help("The port to try to hit at http://localhost:<PORT> (default: 8080)")
                 .validator(positive_integer_validator),
         )
+        .arg(
+            Arg::with_name("transport")
+                .long("transport")
+                .takes_value(true)
+                .possible_values(&["http", "mqtt", "serial", "shmem"])
+                .requires_if("mqtt", "broker")
+                .requires_if("serial", "device")
+                .requires_if("shmem", "path")
+                .help("Transport used to deliver messages (default: http)"),
+        )
+        .arg(
+            Arg::with_name("broker")
+                .long("broker")
+                .takes_value(true)
+                .help("MQTT broker address as host:port, required for --transport mqtt"),
+        )
+        .arg(
+            Arg::with_name("topic")
+                .long("topic")
+                .takes_value(true)
+                .help(
+                    "MQTT topic pattern with {device} and {type} placeholders \
+                     (default: devices/{device}/{type})",
+                ),
+        )
+        .arg(
+            Arg::with_name("device")
+                .long("device")
+                .takes_value(true)
+                .help("Serial device path, e.g. /dev/ttyUSB0, required for --transport serial"),
+        )
+        .arg(
+            Arg::with_name("baud")
+                .long("baud")
+                .takes_value(true)
+                .help("Serial baud rate (default: 115200)")
+                .validator(positive_integer_validator),
+        )
+        .arg(
+            Arg::with_name("crc")
+                .long("crc")
+                .takes_value(true)
+                .possible_values(&["crc16", "crc32"])
+                .help("Checksum appended to each serial frame (default: crc16)"),
+        )
+        .arg(
+            Arg::with_name("path")
+                .long("path")
+                .takes_value(true)
+                .help("Path to the ring buffer file, required for --transport shmem"),
+        )
+        .arg(
+            Arg::with_name("markov-config")
+                .long("markov-config")
+                .takes_value(true)
+                .help(
+                    "Path to a TOML file overriding the device behavior state profiles and \
+                     transition matrix (default: built-in profiles); a --config fleet file's \
+                     own [markov] table is used instead if this is omitted",
+                ),
+        )
+        .arg(
+            Arg::with_name("size")
+                .long("size")
+                .takes_value(true)
+                .help("Size in bytes of the ring buffer's data region (default: 1048576)")
+                .validator(positive_integer_validator),
+        )
+        .arg(
+            Arg::with_name("commands")
+                .long("commands")
+                .takes_value(true)
+                .help(
+                    "Path to an NDJSON file of downlink commands to acknowledge and complete; \
+                     if omitted (with --simulate or --config), commands are polled from \
+                     http://localhost:<port>/commands",
+                ),
+        )
         .get_matches();
 
     let port: u16 = matches.value_of("port").unwrap_or("8080").parse().unwrap();
 
+    let transport: Arc<dyn Transport> = match matches.value_of("transport").unwrap_or("http") {
+        "mqtt" => {
+            let broker = matches
+                .value_of("broker")
+                .expect("--broker is required when --transport mqtt is used");
+            let topic_pattern = matches
+                .value_of("topic")
+                .unwrap_or("devices/{device}/{type}")
+                .to_string();
+            Arc::new(MqttTransport::new(broker, topic_pattern)?)
+        }
+        "serial" => {
+            let device = matches
+                .value_of("device")
+                .expect("--device is required when --transport serial is used");
+            let baud = matches
+                .value_of("baud")
+                .unwrap_or("115200")
+                .parse::<u32>()
+                .expect("Baud rate must be an integer");
+            let crc = matches
+                .value_of("crc")
+                .unwrap_or("crc16")
+                .parse::<framing::Crc>()
+                .expect("Unknown CRC kind");
+            Arc::new(SerialTransport::new(device, baud, crc)?)
+        }
+        "shmem" => {
+            let path = matches
+                .value_of("path")
+                .expect("--path is required when --transport shmem is used");
+            let size = matches
+                .value_of("size")
+                .unwrap_or("1048576")
+                .parse::<u64>()
+                .expect("Size must be an integer");
+            Arc::new(ShmemTransport::new(path, size)?)
+        }
+        _ => Arc::new(HttpTransport::new(port)),
+    };
+
+    let markov_override = matches
+        .value_of("markov-config")
+        .map(load_markov_config)
+        .transpose()?
+        .map(Arc::new);
+
     if matches.is_present("file") {
         let file_path = matches.value_of("file").expect("File path is required");
         let interval = matches
@@ -392,7 +1250,17 @@ async fn main() -> Result<(), Box<dyn Error>> {
             .parse::<u64>()
             .expect("Interval must be a number");
 
-        send_messages_from_file(file_path, interval, port)
+        send_messages_from_file(file_path, interval, transport).await
+    } else if matches.is_present("config") {
+        let config_path = matches.value_of("config").expect("Config path is required");
+        let base_seed = matches
+            .value_of("seed")
+            .unwrap_or("0")
+            .parse::<u64>()
+            .expect("Seed must be an integer");
+
+        spawn_command_responder(matches.value_of("commands"), port, Arc::clone(&transport));
+        run_config_simulation(config_path, transport, markov_override, base_seed).await
     } else {
         let device_count = matches
             .value_of("number")
@@ -406,17 +1274,11 @@ async fn main() -> Result<(), Box<dyn Error>> {
             .parse::<u64>()
             .expect("Buffer size must be an integer > 0");
 
-        let log_interval = matches
-            .value_of("log-interval")
-            .unwrap_or("500")
-            .parse::<u64>()
-            .expect("Log interval must be an integer >= 0");
-
-        let sensor_interval = matches
-            .value_of("sensor-interval")
-            .unwrap_or("500")
+        let tick_interval = matches
+            .value_of("tick-interval")
+            .unwrap_or("100")
             .parse::<u64>()
-            .expect("Sensor interval must be an integer >= 0");
+            .expect("Tick interval must be an integer >= 0");
 
         let write_interval = matches
             .value_of("write-interval")
@@ -424,16 +1286,30 @@ async fn main() -> Result<(), Box<dyn Error>> {
             .parse::<u64>()
             .expect("Write interval must be an integer >= 0");
 
+        let base_seed = matches
+            .value_of("seed")
+            .unwrap_or("0")
+            .parse::<u64>()
+            .expect("Seed must be an integer");
+
+        spawn_command_responder(matches.value_of("commands"), port, Arc::clone(&transport));
+
+        let markov = Arc::new(markov_override.map_or_else(default_markov_config, |m| (*m).clone()));
+
         let mut simulations = Vec::new();
 
-        for _ in 0..device_count {
+        for device_index in 0..device_count {
+            let transport = Arc::clone(&transport);
+            let markov = Arc::clone(&markov);
             simulations.push(tokio::spawn(async move {
                 simulate_messages(
-                    port,
-                    log_interval,
-                    sensor_interval,
+                    transport,
+                    markov,
+                    tick_interval,
                     write_interval,
                     buffer_size,
+                    base_seed,
+                    device_index,
                 )
                 .await;
             }));
@@ -0,0 +1,323 @@
+use crate::framing::{self, Crc};
+use crate::{Message, MessageType};
+use memmap2::{MmapMut, MmapOptions};
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// A sink that simulated devices publish their messages to. The simulation
+/// and buffering logic only ever talks to this trait, so new wire formats
+/// (MQTT, serial, ...) can be added without touching how messages are
+/// produced.
+#[async_trait::async_trait]
+pub(crate) trait Transport: Send + Sync {
+    async fn send(&self, message: &Message) -> Result<(), Box<dyn Error>>;
+}
+
+/// Posts each message as JSON to `http://localhost:<port>`, the mockup's
+/// original (and still default) transport.
+pub(crate) struct HttpTransport {
+    agent: ureq::Agent,
+    port: u16,
+}
+
+impl HttpTransport {
+    pub(crate) fn new(port: u16) -> Self {
+        HttpTransport {
+            agent: ureq::Agent::new(),
+            port,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for HttpTransport {
+    async fn send(&self, message: &Message) -> Result<(), Box<dyn Error>> {
+        match self
+            .agent
+            .post(&format!("http://localhost:{}", self.port))
+            .set("Content-Type", "application/json")
+            .send_json(serde_json::to_value(message)?)
+        {
+            Ok(_) => {
+                println!("Message sent successfully");
+            }
+            Err(ureq::Error::Status(code, response)) => {
+                eprintln!(
+                    "Failed to send message. Code: {}, Status: {}",
+                    code,
+                    response.status()
+                );
+            }
+            Err(e) => {
+                eprintln!("Failed to send message without getting a response: {:?}", e);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Publishes each message as a JSON payload to an MQTT broker, on a topic
+/// derived from the device ID and message type by substituting `{device}`
+/// and `{type}` into `topic_pattern` (default `devices/{device}/{type}`).
+pub(crate) struct MqttTransport {
+    client: rumqttc::AsyncClient,
+    topic_pattern: String,
+}
+
+impl MqttTransport {
+    /// Connects to `broker` (`host:port`) and spawns a background task that
+    /// drives the MQTT event loop for the lifetime of the process.
+    pub(crate) fn new(broker: &str, topic_pattern: String) -> Result<Self, Box<dyn Error>> {
+        let (host, port) = broker
+            .split_once(':')
+            .ok_or("--broker must be of the form host:port")?;
+        let port: u16 = port.parse()?;
+
+        let mut mqtt_options = rumqttc::MqttOptions::new("iot-telem-mockup", host, port);
+        mqtt_options.set_keep_alive(Duration::from_secs(5));
+        let (client, mut event_loop) = rumqttc::AsyncClient::new(mqtt_options, 64);
+
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = event_loop.poll().await {
+                    eprintln!("MQTT connection error: {:?}", e);
+                }
+            }
+        });
+
+        Ok(MqttTransport {
+            client,
+            topic_pattern,
+        })
+    }
+
+    fn topic_for(&self, message: &Message) -> String {
+        let message_type = match message.message_type {
+            MessageType::Log => "log",
+            MessageType::SensorData => "sensorData",
+            MessageType::ActionResponse => "actionResponse",
+        };
+        self.topic_pattern
+            .replace("{device}", &message.device)
+            .replace("{type}", message_type)
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for MqttTransport {
+    async fn send(&self, message: &Message) -> Result<(), Box<dyn Error>> {
+        let topic = self.topic_for(message);
+        let payload = serde_json::to_vec(message)?;
+        self.client
+            .publish(topic, rumqttc::QoS::AtLeastOnce, false, payload)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Writes each message to a serial port as a COBS-framed, CRC-checked
+/// packet, mirroring how real embedded telemetry links frame their traffic.
+/// Suitable for firmware-in-the-loop testing where the receiver decodes raw
+/// bytes rather than JSON-over-HTTP. The device path is opened only as a
+/// serial port (with the given baud rate); plain files and named pipes are
+/// not supported as outputs.
+pub(crate) struct SerialTransport {
+    port: Mutex<Box<dyn serialport::SerialPort>>,
+    crc: Crc,
+}
+
+impl SerialTransport {
+    pub(crate) fn new(device: &str, baud: u32, crc: Crc) -> Result<Self, Box<dyn Error>> {
+        let port = serialport::new(device, baud)
+            .timeout(Duration::from_millis(500))
+            .open()?;
+        Ok(SerialTransport {
+            port: Mutex::new(port),
+            crc,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for SerialTransport {
+    async fn send(&self, message: &Message) -> Result<(), Box<dyn Error>> {
+        let payload = serde_json::to_vec(message)?;
+        let framed = framing::frame(&payload, self.crc);
+        let mut port = self.port.lock().unwrap();
+        port.write_all(&framed)?;
+        Ok(())
+    }
+}
+
+/// Bytes reserved at the front of the ring buffer file for the atomic
+/// reserve/write/read offsets (one `u64` each), ahead of the circular data
+/// region.
+const RING_HEADER_SIZE: u64 = 24;
+
+/// Length-prefix value marking an abandoned tail: a producer that wrapped
+/// before reaching the physical end of the buffer writes this instead of a
+/// real length, so the consumer knows to jump back to offset 0 rather than
+/// mis-parse whatever bytes were left there.
+const SENTINEL_LEN: u32 = u32::MAX;
+
+/// Writes length-prefixed JSON records into a memory-mapped circular
+/// buffer for high-throughput local testing without HTTP/TCP overhead.
+///
+/// Producers - including in separate processes that mmap the same file -
+/// reserve a byte range by advancing the shared `reserve_offset` with a CAS
+/// loop, so the reservation itself is race-free across process boundaries
+/// (a `Mutex` only ever protects this process's own threads). Once a
+/// producer owns its range it writes directly into the mapping through a
+/// raw pointer, then spins until `write_offset` reaches the start of its
+/// range before publishing the end of its range with a release store; this
+/// keeps publication in reservation order even if two producers finish
+/// writing out of order. A separate consumer process reads sequentially
+/// from `read_offset`, using acquire loads on `write_offset` to know when
+/// more data is ready, and treats [`SENTINEL_LEN`] as "wrap to 0" instead
+/// of a real record.
+pub(crate) struct ShmemTransport {
+    // Pointer to the start of the mapping, i.e. to the header; the data
+    // region begins `RING_HEADER_SIZE` bytes past it (see `write_at`).
+    // `_mmap` is kept only to hold the mapping alive; all actual
+    // reads/writes go through `data`.
+    data: *mut u8,
+    _mmap: MmapMut,
+    capacity: u64,
+}
+
+// SAFETY: all access to `data` goes through the atomics-guarded reserve/
+// publish protocol in `send`, which hands each producer an exclusive,
+// disjoint byte range before it ever writes through the pointer.
+unsafe impl Send for ShmemTransport {}
+unsafe impl Sync for ShmemTransport {}
+
+impl ShmemTransport {
+    pub(crate) fn new(path: &str, size: u64) -> Result<Self, Box<dyn Error>> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(path)?;
+        file.set_len(RING_HEADER_SIZE + size)?;
+        let mut mmap = unsafe { MmapOptions::new().map_mut(&file)? };
+        let data = mmap.as_mut_ptr();
+        Ok(ShmemTransport {
+            data,
+            _mmap: mmap,
+            capacity: size,
+        })
+    }
+
+    fn reserve_offset(&self) -> &AtomicU64 {
+        unsafe { &*(self.data as *const AtomicU64) }
+    }
+
+    fn write_offset(&self) -> &AtomicU64 {
+        unsafe { &*(self.data.add(8) as *const AtomicU64) }
+    }
+
+    fn read_offset(&self) -> &AtomicU64 {
+        unsafe { &*(self.data.add(16) as *const AtomicU64) }
+    }
+
+    /// Copies `bytes` into the data region at `relative_offset` (i.e.
+    /// relative to the start of the data region, excluding the header).
+    ///
+    /// SAFETY: caller must hold an exclusive reservation covering
+    /// `[relative_offset, relative_offset + bytes.len())`.
+    unsafe fn write_at(&self, relative_offset: u64, bytes: &[u8]) {
+        let start = self.data.add((RING_HEADER_SIZE + relative_offset) as usize);
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), start, bytes.len());
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for ShmemTransport {
+    async fn send(&self, message: &Message) -> Result<(), Box<dyn Error>> {
+        let payload = serde_json::to_vec(message)?;
+        let record_size = 4 + payload.len() as u64; // u32 length prefix + payload
+        if record_size > self.capacity {
+            return Err("message does not fit in the ring buffer".into());
+        }
+
+        // Reserve a byte range by advancing the shared `reserve_offset`
+        // with a CAS loop: if another producer (in this process or
+        // another) claims the range first, our compare_exchange fails and
+        // we retry with freshly observed offsets.
+        let (old_reserve_offset, reserved_at, new_reserve_offset, wraps, tail_padding) = loop {
+            let reserve_offset = self.reserve_offset().load(Ordering::Relaxed);
+            let read_offset = self.read_offset().load(Ordering::Acquire);
+
+            let wraps = reserve_offset + record_size > self.capacity;
+            let tail_padding = if wraps { self.capacity - reserve_offset } else { 0 };
+            if wraps && tail_padding > 0 && tail_padding < 4 {
+                // Too little room left to even write the sentinel marker.
+                // We still must advance past this dead zone - returning
+                // here without moving `reserve_offset` would leave every
+                // future call recomputing this exact same "wraps, tiny
+                // tail" condition and dropping forever. Claim the dead
+                // bytes with their own reserve/publish round (writing
+                // nothing) and retry the real reservation from offset 0.
+                if self
+                    .reserve_offset()
+                    .compare_exchange(reserve_offset, 0, Ordering::AcqRel, Ordering::Relaxed)
+                    .is_ok()
+                {
+                    while self.write_offset().load(Ordering::Acquire) != reserve_offset {
+                        std::hint::spin_loop();
+                    }
+                    self.write_offset().store(0, Ordering::Release);
+                }
+                continue;
+            }
+            let reserved_at = if wraps { 0 } else { reserve_offset };
+            let new_reserve_offset = reserved_at + record_size;
+
+            let used = if reserve_offset >= read_offset {
+                reserve_offset - read_offset
+            } else {
+                self.capacity - (read_offset - reserve_offset)
+            };
+            if used + tail_padding + record_size > self.capacity {
+                eprintln!("Shared-memory ring buffer full; dropping message");
+                return Ok(());
+            }
+
+            if self
+                .reserve_offset()
+                .compare_exchange(reserve_offset, new_reserve_offset, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+            {
+                break (reserve_offset, reserved_at, new_reserve_offset, wraps, tail_padding);
+            }
+        };
+
+        // SAFETY: the successful CAS above exclusively granted this
+        // producer the range [old_reserve_offset, new_reserve_offset)
+        // (wrapping through 0 if `wraps`) - no other producer can have
+        // claimed any part of it, so writing through the raw pointer here
+        // cannot race with another producer's write.
+        unsafe {
+            if wraps && tail_padding >= 4 {
+                self.write_at(old_reserve_offset, &SENTINEL_LEN.to_le_bytes());
+            }
+            self.write_at(reserved_at, &(payload.len() as u32).to_le_bytes());
+            self.write_at(reserved_at + 4, &payload);
+        }
+
+        // Publish in reservation order: wait until every producer that
+        // reserved before us has published, then advance `write_offset`
+        // with a release store so the consumer's acquire load is
+        // guaranteed to observe the bytes written above.
+        while self.write_offset().load(Ordering::Acquire) != old_reserve_offset {
+            std::hint::spin_loop();
+        }
+        self.write_offset().store(new_reserve_offset, Ordering::Release);
+
+        Ok(())
+    }
+}